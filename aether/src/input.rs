@@ -0,0 +1,208 @@
+use std::collections::HashSet;
+use winit::event::{
+    DeviceEvent, ElementState, Event, KeyboardInput, MouseButton, MouseScrollDelta, VirtualKeyCode,
+    WindowEvent,
+};
+
+#[derive(Clone, Debug, Default)]
+pub struct Input {
+    keys_down: HashSet<VirtualKeyCode>,
+    keys_pressed: HashSet<VirtualKeyCode>,
+    mouse_buttons_down: HashSet<MouseButton>,
+    mouse_position: [f64; 2],
+    mouse_delta: [f64; 2],
+    scroll_delta: [f64; 2],
+}
+
+impl Input {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn handle_event<T>(&mut self, event: &Event<T>) {
+        match event {
+            Event::WindowEvent { event, .. } => self.handle_window_event(event),
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } => {
+                self.mouse_delta[0] += delta.0;
+                self.mouse_delta[1] += delta.1;
+            }
+            _ => (),
+        }
+    }
+
+    #[doc = "Deltas and presses accumulate over every event since the last call, so call this once the frame has read them"]
+    pub fn end_frame(&mut self) {
+        self.keys_pressed.clear();
+        self.mouse_delta = [0.0, 0.0];
+        self.scroll_delta = [0.0, 0.0];
+    }
+
+    #[must_use]
+    pub fn is_key_down(&self, key: VirtualKeyCode) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    #[doc = "Whether `key` went down since the last `end_frame`, even if it has been released again since"]
+    #[must_use]
+    pub fn was_key_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    #[must_use]
+    pub fn is_mouse_button_down(&self, button: MouseButton) -> bool {
+        self.mouse_buttons_down.contains(&button)
+    }
+
+    #[must_use]
+    pub const fn mouse_position(&self) -> [f64; 2] {
+        self.mouse_position
+    }
+
+    #[must_use]
+    pub const fn mouse_delta(&self) -> [f64; 2] {
+        self.mouse_delta
+    }
+
+    #[doc = "Wheels report lines, touchpads report pixels"]
+    #[must_use]
+    pub const fn scroll_delta(&self) -> [f64; 2] {
+        self.scroll_delta
+    }
+
+    fn handle_window_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => match state {
+                // Held keys repeat their press events, only the first one counts as a press
+                ElementState::Pressed => {
+                    if self.keys_down.insert(*key) {
+                        self.keys_pressed.insert(*key);
+                    }
+                }
+                ElementState::Released => {
+                    self.keys_down.remove(key);
+                }
+            },
+            WindowEvent::MouseInput { state, button, .. } => match state {
+                ElementState::Pressed => {
+                    self.mouse_buttons_down.insert(*button);
+                }
+                ElementState::Released => {
+                    self.mouse_buttons_down.remove(button);
+                }
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                self.mouse_position = [position.x, position.y];
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (x, y) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (f64::from(*x), f64::from(*y)),
+                    MouseScrollDelta::PixelDelta(position) => (position.x, position.y),
+                };
+                self.scroll_delta[0] += x;
+                self.scroll_delta[1] += y;
+            }
+            // Releases are never seen while unfocused, so anything held would otherwise stick
+            WindowEvent::Focused(false) => {
+                self.keys_down.clear();
+                self.mouse_buttons_down.clear();
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(deprecated, clippy::float_cmp)]
+mod tests {
+    use super::*;
+    use winit::{
+        event::{DeviceId, ModifiersState, TouchPhase},
+        window::WindowId,
+    };
+
+    fn window_event(event: WindowEvent<'static>) -> Event<'static, ()> {
+        Event::WindowEvent {
+            window_id: unsafe { WindowId::dummy() },
+            event,
+        }
+    }
+
+    fn key_event(key: VirtualKeyCode, state: ElementState) -> Event<'static, ()> {
+        window_event(WindowEvent::KeyboardInput {
+            device_id: unsafe { DeviceId::dummy() },
+            input: KeyboardInput {
+                scancode: 0,
+                state,
+                virtual_keycode: Some(key),
+                modifiers: ModifiersState::empty(),
+            },
+            is_synthetic: false,
+        })
+    }
+
+    #[test]
+    fn tracks_key_presses() {
+        let mut input = Input::new();
+        input.handle_event(&key_event(VirtualKeyCode::W, ElementState::Pressed));
+        assert!(input.is_key_down(VirtualKeyCode::W));
+        assert!(!input.is_key_down(VirtualKeyCode::S));
+
+        input.handle_event(&key_event(VirtualKeyCode::W, ElementState::Released));
+        assert!(!input.is_key_down(VirtualKeyCode::W));
+    }
+
+    #[test]
+    fn press_and_release_in_one_frame_is_still_a_press() {
+        let mut input = Input::new();
+        input.handle_event(&key_event(VirtualKeyCode::Escape, ElementState::Pressed));
+        input.handle_event(&key_event(VirtualKeyCode::Escape, ElementState::Released));
+        assert!(input.was_key_pressed(VirtualKeyCode::Escape));
+        assert!(!input.is_key_down(VirtualKeyCode::Escape));
+
+        input.end_frame();
+        assert!(!input.was_key_pressed(VirtualKeyCode::Escape));
+    }
+
+    #[test]
+    fn deltas_accumulate_until_end_frame() {
+        let mut input = Input::new();
+        for _ in 0..2 {
+            input.handle_event::<()>(&Event::DeviceEvent {
+                device_id: unsafe { DeviceId::dummy() },
+                event: DeviceEvent::MouseMotion { delta: (1.5, -2.0) },
+            });
+            input.handle_event(&window_event(WindowEvent::MouseWheel {
+                device_id: unsafe { DeviceId::dummy() },
+                delta: MouseScrollDelta::LineDelta(0.0, 1.0),
+                phase: TouchPhase::Moved,
+                modifiers: ModifiersState::empty(),
+            }));
+        }
+        assert_eq!(input.mouse_delta(), [3.0, -4.0]);
+        assert_eq!(input.scroll_delta(), [0.0, 2.0]);
+
+        input.end_frame();
+        assert_eq!(input.mouse_delta(), [0.0, 0.0]);
+        assert_eq!(input.scroll_delta(), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn losing_focus_releases_keys() {
+        let mut input = Input::new();
+        input.handle_event(&key_event(VirtualKeyCode::A, ElementState::Pressed));
+        input.handle_event(&window_event(WindowEvent::Focused(false)));
+        assert!(!input.is_key_down(VirtualKeyCode::A));
+    }
+}
@@ -3,6 +3,7 @@
 #![warn(clippy::unwrap_used)]
 #![warn(clippy::expect_used)]
 
+pub mod input;
 pub mod prelude;
 pub mod vulkan;
 
@@ -1,3 +1,5 @@
+pub mod arcball;
+
 use std::fmt::Debug;
 
 #[derive(Clone, Debug, Default)]
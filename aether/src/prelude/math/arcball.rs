@@ -0,0 +1,53 @@
+use nalgebra_glm as glm;
+
+#[doc = "Points inside the sphere's silhouette are projected onto the sphere, points outside onto a hyperbolic sheet so dragging past the edge keeps rotating smoothly instead of snapping"]
+#[must_use]
+pub fn project(point: &glm::Vec2, radius: f32) -> glm::Vec3 {
+    let length_squared = glm::length2(point);
+    let radius_squared = radius * radius;
+
+    let z = if length_squared <= radius_squared / 2.0 {
+        (radius_squared - length_squared).sqrt()
+    } else {
+        (radius_squared / 2.0) / length_squared.sqrt()
+    };
+
+    glm::normalize(&glm::vec3(point.x, point.y, z))
+}
+
+#[doc = "Rotation between two normalized screen positions, in the range -1 to 1 on each axis"]
+#[must_use]
+pub fn rotation(from: &glm::Vec2, to: &glm::Vec2, radius: f32) -> glm::Quat {
+    glm::quat_rotation(&project(from, radius), &project(to, radius))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rotate(rotation: &glm::Quat, vector: &glm::Vec3) -> glm::Vec3 {
+        glm::quat_rotate_vec3(rotation, vector)
+    }
+
+    #[test]
+    fn same_point_is_identity() {
+        let point = glm::vec2(0.25, -0.5);
+        let result = rotation(&point, &point, 1.0);
+        assert!(glm::length(&(rotate(&result, &glm::Vec3::x()) - glm::Vec3::x())) < 1e-5);
+    }
+
+    #[test]
+    fn horizontal_drag_rotates_around_y() {
+        let result = rotation(&glm::vec2(0.0, 0.0), &glm::vec2(0.5, 0.0), 1.0);
+        let up = rotate(&result, &glm::Vec3::y());
+        assert!(glm::length(&(up - glm::Vec3::y())) < 1e-5);
+        assert!(rotate(&result, &glm::Vec3::z()).x > 0.0);
+    }
+
+    #[test]
+    fn outside_sphere_projects_onto_sheet() {
+        let projected = project(&glm::vec2(2.0, 0.0), 1.0);
+        assert!((glm::length(&projected) - 1.0).abs() < 1e-5);
+        assert!(projected.z > 0.0);
+    }
+}
@@ -1,2 +1,5 @@
 pub mod context;
+pub mod memory;
+pub mod pipeline;
+pub mod upload;
 pub mod vertex;
@@ -1,17 +1,76 @@
-use eyre::Result;
+use eyre::{eyre, Result};
+use log::{info, warn};
 use std::{collections::HashSet, sync::Arc};
 use vulkano::{
     device::{
-        physical::{PhysicalDevice, QueueFamily},
-        Device, DeviceCreateInfo, DeviceExtensions, Queue, QueueCreateInfo,
+        physical::{MemoryHeap, PhysicalDevice, PhysicalDeviceType, QueueFamily},
+        Device, DeviceCreateInfo, DeviceExtensions, Features, Queue, QueueCreateInfo,
     },
     format::Format,
     image::{ImageUsage, SwapchainImage},
     instance::{Instance, InstanceCreateInfo},
     swapchain::{ColorSpace, PresentMode, Surface, SurfaceInfo, Swapchain, SwapchainCreateInfo},
+    DeviceSize,
 };
-use vulkano_win::VkSurfaceBuild;
-use winit::{event_loop::EventLoop, window::WindowBuilder};
+use winit::{
+    dpi::PhysicalSize,
+    event_loop::EventLoop,
+    window::{Fullscreen, Window, WindowBuilder},
+};
+
+use super::memory::{self, MemoryReport};
+use crate::prelude::math::Size;
+
+#[derive(Clone, Debug)]
+pub struct DeviceInfo {
+    pub index: usize,
+    pub name: String,
+    pub device_type: PhysicalDeviceType,
+    pub memory: DeviceSize,
+    pub suitable: bool,
+}
+
+#[doc = "Trades power use against input latency when picking the swapchain's present mode"]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PresentModePolicy {
+    #[doc = "FIFO, waits for vblank so the GPU idles between frames, which keeps mostly static scenes from draining battery"]
+    #[default]
+    LowPower,
+    #[doc = "Mailbox, or Immediate without it, presents as soon as a frame is ready for lower latency, but renders uncapped and uses more power. Falls back to FIFO when neither is supported"]
+    LowLatency,
+}
+
+#[derive(Clone, Debug)]
+pub struct ContextCreateInfo {
+    pub title: Option<String>,
+    pub size: Option<Size<u32>>,
+    pub resizable: bool,
+    pub fullscreen: Option<Fullscreen>,
+    #[doc = "Device features that must be enabled, context creation fails if the device is missing any of them"]
+    pub features: Features,
+    #[doc = "Device features to enable where supported, e.g. `wide_lines` so `Context::line_width` can go past 1.0"]
+    pub optional_features: Features,
+    #[doc = "Index of the physical device to use, as listed by `Context::enumerate_devices`, the first suitable device is used when unset"]
+    pub device_index: Option<usize>,
+    pub surface_formats: Vec<(Format, ColorSpace)>,
+    pub present_mode_policy: PresentModePolicy,
+}
+
+impl Default for ContextCreateInfo {
+    fn default() -> Self {
+        Self {
+            title: None,
+            size: None,
+            resizable: true,
+            fullscreen: None,
+            features: Features::none(),
+            optional_features: Features::none(),
+            device_index: None,
+            surface_formats: vec![(Format::B8G8R8A8_SRGB, ColorSpace::SrgbNonLinear)],
+            present_mode_policy: PresentModePolicy::default(),
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct Context {
@@ -25,36 +84,71 @@ pub struct Context {
 
 impl Context {
     #[doc = "# Panics"]
-    #[doc = "# Panics if the device is unsuitable for aether, or if an internal vulkan error occurs"]
-    pub fn new(event_loop: &EventLoop<()>) -> Result<Self> {
-        let required_extensions = vulkano_win::required_extensions();
-        let instance = match Instance::new(InstanceCreateInfo {
-            enabled_extensions: required_extensions,
-            ..Default::default()
-        }) {
-            Ok(instance) => instance,
-            Err(e) => panic!("Failed to create vulkan instance because {}", e),
+    #[doc = "# Panics if an internal vulkan error occurs"]
+    #[doc = "# Errors"]
+    #[doc = "# Errors if no suitable device is found, if the device at `create_info.device_index` is missing or unsuitable, or if it does not support every feature in `create_info.features`"]
+    pub fn new(event_loop: &EventLoop<()>, create_info: &ContextCreateInfo) -> Result<Self> {
+        let window = match Self::window_builder(create_info).build(event_loop) {
+            Ok(window) => window,
+            Err(e) => panic!("Failed to create window because {}", e),
         };
 
-        let surface = match WindowBuilder::new().build_vk_surface(event_loop, instance.clone()) {
+        Self::from_window(window, create_info)
+    }
+
+    #[doc = "Lists every physical device, `DeviceInfo::index` can be passed as `ContextCreateInfo::device_index`"]
+    #[doc = "# Errors"]
+    #[doc = "# Errors if the hidden window used for checking present support can't be created"]
+    pub fn enumerate_devices(event_loop: &EventLoop<()>) -> Result<Vec<DeviceInfo>> {
+        let instance = Self::create_instance();
+        let window = WindowBuilder::new()
+            .with_visible(false)
+            .build(event_loop)
+            .map_err(|e| eyre!("Failed to create window because {}", e))?;
+        let surface = vulkano_win::create_surface_from_winit(window, instance.clone())
+            .map_err(|e| eyre!("Failed to create surface because {}", e))?;
+
+        Ok(PhysicalDevice::enumerate(&instance)
+            .map(|physical| DeviceInfo {
+                index: physical.index(),
+                name: physical.properties().device_name.clone(),
+                device_type: physical.properties().device_type,
+                memory: physical
+                    .memory_heaps()
+                    .filter(MemoryHeap::is_device_local)
+                    .map(|heap| heap.size())
+                    .sum(),
+                suitable: Self::is_suitable(physical, &surface),
+            })
+            .collect())
+    }
+
+    #[doc = "Creates a context presenting to an existing window, the window options in `create_info` are ignored"]
+    #[doc = "# Panics"]
+    #[doc = "# Panics if an internal vulkan error occurs"]
+    #[doc = "# Errors"]
+    #[doc = "# Errors if no suitable device is found, if the device at `create_info.device_index` is missing or unsuitable, or if it does not support every feature in `create_info.features`"]
+    pub fn from_window(window: Window, create_info: &ContextCreateInfo) -> Result<Self> {
+        let instance = Self::create_instance();
+
+        let surface = match vulkano_win::create_surface_from_winit(window, instance.clone()) {
             Ok(surface) => surface,
             Err(e) => panic!("Failed to create surface because {}", e),
         };
 
-        let device_extensions = DeviceExtensions {
-            khr_swapchain: true,
+        let physical = Self::pick_physical_device(&instance, &surface, create_info.device_index)?;
+        let device_extensions = Self::device_extensions().union(&DeviceExtensions {
+            ext_memory_budget: memory::supports_budget(physical),
             ..DeviceExtensions::none()
-        };
-        let physical = match PhysicalDevice::enumerate(&instance).find(|physical| {
-            physical
-                .supported_extensions()
-                .is_superset_of(&device_extensions)
-        }) {
-            Some(physical) => physical,
-            None => panic!("No devices supporting vulkan found"),
-        };
+        });
 
-        println!("Using device {}", physical.properties().device_name);
+        info!("Using device {}", physical.properties().device_name);
+        Self::check_features(physical, &create_info.features)?;
+        let features = Self::enabled_features(
+            physical.supported_features(),
+            &create_info.features,
+            &create_info.optional_features,
+        );
 
         let graphics_family = match physical
             .queue_families()
@@ -101,6 +195,7 @@ impl Context {
             DeviceCreateInfo {
                 queue_create_infos,
                 enabled_extensions: device_extensions,
+                enabled_features: features,
                 ..Default::default()
             },
         ) {
@@ -121,7 +216,7 @@ impl Context {
             }
         }
 
-        let (swapchain, images) = Self::create_swapchain(&device, &surface);
+        let (swapchain, images) = Self::create_swapchain(&device, &surface, create_info);
 
         Ok(Self {
             surface,
@@ -139,9 +234,115 @@ impl Context {
         })
     }
 
+    // Waiting on the queues rather than the device keeps this safe, and every submission goes through them
+    #[doc = "# Errors"]
+    #[doc = "# Errors if the device runs out of memory while waiting"]
+    pub fn wait_idle(&self) -> Result<()> {
+        self.graphics
+            .wait()
+            .and_then(|()| self.present.wait())
+            .map_err(|e| eyre!("Failed to wait for device idle because {}", e))
+    }
+
+    #[doc = "# Errors"]
+    #[doc = "# Errors if the device can't be waited on or the swapchain can't be recreated"]
+    pub fn recreate_swapchain(&mut self, new_extent: [u32; 2]) -> Result<()> {
+        self.recreate_swapchain_with(SwapchainCreateInfo {
+            image_extent: new_extent,
+            ..self.swapchain.create_info()
+        })
+    }
+
+    #[doc = "# Errors"]
+    #[doc = "# Errors if the present modes can't be queried or the swapchain can't be recreated"]
+    pub fn set_present_mode_policy(&mut self, policy: PresentModePolicy) -> Result<()> {
+        let present_mode = Self::pick_present_mode(&self.device, &self.surface, policy)?;
+        if present_mode == self.swapchain.present_mode() {
+            return Ok(());
+        }
+
+        self.recreate_swapchain_with(SwapchainCreateInfo {
+            present_mode,
+            ..self.swapchain.create_info()
+        })
+    }
+
+    #[must_use]
+    pub fn present_mode(&self) -> PresentMode {
+        self.swapchain.present_mode()
+    }
+
+    #[doc = "Clamps `width` to what the device can rasterize, widths other than 1.0 need the `wide_lines` feature"]
+    #[must_use]
+    pub fn line_width(&self, width: f32) -> f32 {
+        if self.device.enabled_features().wide_lines {
+            let [min, max] = self.device.physical_device().properties().line_width_range;
+            width.clamp(min, max)
+        } else {
+            if (width - 1.0).abs() > f32::EPSILON {
+                warn!(
+                    "Line width {} requires the wide_lines feature, falling back to 1.0",
+                    width
+                );
+            }
+            1.0
+        }
+    }
+
+    #[doc = "Per heap memory sizes, with current usage and budget when the device supports `VK_EXT_memory_budget`"]
+    #[must_use]
+    pub fn memory_report(&self) -> MemoryReport {
+        memory::report(&self.device)
+    }
+
+    #[must_use]
+    pub fn surface_format(&self) -> (Format, ColorSpace) {
+        (
+            self.swapchain.image_format(),
+            self.swapchain.image_color_space(),
+        )
+    }
+
+    #[must_use]
+    pub fn window(&self) -> &Window {
+        self.surface.window()
+    }
+
+    #[must_use]
+    pub fn window_size(&self) -> [u32; 2] {
+        self.window().inner_size().into()
+    }
+
+    fn recreate_swapchain_with(&mut self, create_info: SwapchainCreateInfo) -> Result<()> {
+        self.wait_idle()?;
+
+        let (swapchain, images) = self
+            .swapchain
+            .recreate(create_info)
+            .map_err(|e| eyre!("Failed to recreate swapchain because {}", e))?;
+
+        self.swapchain = swapchain;
+        self.images = images;
+        Ok(())
+    }
+
+    fn window_builder(create_info: &ContextCreateInfo) -> WindowBuilder {
+        let mut builder = WindowBuilder::new()
+            .with_resizable(create_info.resizable)
+            .with_fullscreen(create_info.fullscreen.clone());
+        if let Some(title) = &create_info.title {
+            builder = builder.with_title(title);
+        }
+        if let Some(size) = &create_info.size {
+            builder = builder.with_inner_size(PhysicalSize::new(size.width, size.height));
+        }
+        builder
+    }
+
     fn create_swapchain(
         device: &Arc<Device>,
         surface: &Arc<Surface<winit::window::Window>>,
+        create_info: &ContextCreateInfo,
     ) -> (
         Arc<Swapchain<winit::window::Window>>,
         Vec<Arc<SwapchainImage<winit::window::Window>>>,
@@ -160,32 +361,19 @@ impl Context {
             Ok(formats) => formats,
             Err(e) => panic!("Failed to get surface formats because {}", e),
         };
-        let mut modes = match device.physical_device().surface_present_modes(surface) {
-            Ok(modes) => modes,
-            Err(e) => panic!("Failed to get surface present modes because {}", e),
-        };
 
         let mut num_images = capabilities.min_image_count + 1;
         if let Some(max_images) = capabilities.max_image_count {
             num_images = num_images.min(max_images);
         }
 
-        let (format, colorspace) = formats
-            .iter()
-            .find(|(format, colorspace)| {
-                *format == Format::B8G8R8A8_SRGB && *colorspace == ColorSpace::SrgbNonLinear
-            })
-            .map_or_else(
-                || match formats.first() {
-                    Some(format) => format,
-                    None => panic!("No surface formats"),
-                },
-                |format| format,
-            );
-
-        let mode = modes
-            .find(|mode| *mode == PresentMode::Mailbox)
-            .map_or(PresentMode::Fifo, |mode| mode);
+        let (format, colorspace) =
+            Self::pick_surface_format(&formats, &create_info.surface_formats);
+
+        let mode = match Self::pick_present_mode(device, surface, create_info.present_mode_policy) {
+            Ok(mode) => mode,
+            Err(e) => panic!("{}", e),
+        };
 
         let dimensions = surface.window().inner_size();
         let composite_alpha = match capabilities.supported_composite_alpha.iter().next() {
@@ -198,8 +386,8 @@ impl Context {
             surface.clone(),
             SwapchainCreateInfo {
                 min_image_count: num_images,
-                image_format: Some(*format),
-                image_color_space: *colorspace,
+                image_format: Some(format),
+                image_color_space: colorspace,
                 present_mode: mode,
                 image_extent: dimensions.into(),
                 composite_alpha,
@@ -211,4 +399,230 @@ impl Context {
             Err(e) => panic!("Failed to create swapchain because {}", e)
         }
     }
+
+    fn create_instance() -> Arc<Instance> {
+        match Instance::new(InstanceCreateInfo {
+            enabled_extensions: vulkano_win::required_extensions(),
+            ..Default::default()
+        }) {
+            Ok(instance) => instance,
+            Err(e) => panic!("Failed to create vulkan instance because {}", e),
+        }
+    }
+
+    const fn device_extensions() -> DeviceExtensions {
+        DeviceExtensions {
+            khr_swapchain: true,
+            ..DeviceExtensions::none()
+        }
+    }
+
+    fn is_suitable(physical: PhysicalDevice, surface: &Arc<Surface<Window>>) -> bool {
+        physical
+            .supported_extensions()
+            .is_superset_of(&Self::device_extensions())
+            && physical
+                .queue_families()
+                .any(|family| family.supports_graphics())
+            && physical
+                .queue_families()
+                .any(|family| family.supports_surface(surface).unwrap_or(false))
+    }
+
+    fn pick_present_mode(
+        device: &Arc<Device>,
+        surface: &Arc<Surface<Window>>,
+        policy: PresentModePolicy,
+    ) -> Result<PresentMode> {
+        let modes: Vec<PresentMode> = device
+            .physical_device()
+            .surface_present_modes(surface)
+            .map_err(|e| eyre!("Failed to get surface present modes because {}", e))?
+            .collect();
+
+        Ok(Self::present_mode_for_policy(&modes, policy))
+    }
+
+    // FIFO is the only mode every device is required to support, so it is always the fallback
+    fn present_mode_for_policy(
+        supported: &[PresentMode],
+        policy: PresentModePolicy,
+    ) -> PresentMode {
+        let preferred: &[PresentMode] = match policy {
+            PresentModePolicy::LowPower => &[PresentMode::Fifo],
+            PresentModePolicy::LowLatency => &[PresentMode::Mailbox, PresentMode::Immediate],
+        };
+
+        preferred
+            .iter()
+            .copied()
+            .find(|mode| supported.contains(mode))
+            .unwrap_or(PresentMode::Fifo)
+    }
+
+    fn pick_surface_format(
+        available: &[(Format, ColorSpace)],
+        preferred: &[(Format, ColorSpace)],
+    ) -> (Format, ColorSpace) {
+        if let Some(format) = preferred.iter().find(|format| available.contains(format)) {
+            return *format;
+        }
+
+        match available.first() {
+            Some(format) => {
+                warn!(
+                    "None of the preferred surface formats are supported, falling back to {:?}",
+                    format
+                );
+                *format
+            }
+            None => panic!("No surface formats"),
+        }
+    }
+
+    fn pick_physical_device<'a>(
+        instance: &'a Arc<Instance>,
+        surface: &Arc<Surface<Window>>,
+        device_index: Option<usize>,
+    ) -> Result<PhysicalDevice<'a>> {
+        match device_index {
+            Some(index) => match PhysicalDevice::from_index(instance, index) {
+                Some(physical) if Self::is_suitable(physical, surface) => Ok(physical),
+                Some(physical) => Err(eyre!(
+                    "Device {} is not suitable for aether",
+                    physical.properties().device_name
+                )),
+                None => Err(eyre!("No device with index {}", index)),
+            },
+            None => match PhysicalDevice::enumerate(instance)
+                .find(|physical| Self::is_suitable(*physical, surface))
+            {
+                Some(physical) => Ok(physical),
+                None => Err(eyre!("No device suitable for aether found")),
+            },
+        }
+    }
+
+    // Features has no union, but once the required features are known to be supported, the union with the
+    // supported optional ones is every supported feature that isn't missing from both
+    const fn enabled_features(
+        supported: &Features,
+        required: &Features,
+        optional: &Features,
+    ) -> Features {
+        supported.difference(
+            &supported
+                .difference(required)
+                .intersection(&supported.difference(optional)),
+        )
+    }
+
+    fn check_features(physical: PhysicalDevice, features: &Features) -> Result<()> {
+        let unsupported_features = features.difference(physical.supported_features());
+        if unsupported_features == Features::none() {
+            Ok(())
+        } else {
+            Err(eyre!(
+                "Device {} does not support requested features: {}",
+                physical.properties().device_name,
+                Self::feature_names(&unsupported_features).join(", ")
+            ))
+        }
+    }
+
+    // Features has no way to iterate its members, so the names are pulled out of its Debug output
+    fn feature_names(features: &Features) -> Vec<String> {
+        format!("{:?}", features)
+            .split(['{', ',', '}'])
+            .filter_map(|field| field.trim().strip_suffix(": true"))
+            .map(String::from)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn surface_format_follows_preference_order() {
+        let available = [
+            (Format::B8G8R8A8_UNORM, ColorSpace::SrgbNonLinear),
+            (Format::B8G8R8A8_SRGB, ColorSpace::SrgbNonLinear),
+        ];
+        let preferred = [
+            (Format::R16G16B16A16_SFLOAT, ColorSpace::ExtendedSrgbLinear),
+            (Format::B8G8R8A8_SRGB, ColorSpace::SrgbNonLinear),
+        ];
+        assert_eq!(
+            Context::pick_surface_format(&available, &preferred),
+            (Format::B8G8R8A8_SRGB, ColorSpace::SrgbNonLinear)
+        );
+    }
+
+    #[test]
+    fn present_mode_follows_policy() {
+        let supported = [PresentMode::Fifo, PresentMode::Immediate];
+        assert_eq!(
+            Context::present_mode_for_policy(&supported, PresentModePolicy::LowPower),
+            PresentMode::Fifo
+        );
+        assert_eq!(
+            Context::present_mode_for_policy(&supported, PresentModePolicy::LowLatency),
+            PresentMode::Immediate
+        );
+        assert_eq!(
+            Context::present_mode_for_policy(&[PresentMode::Fifo], PresentModePolicy::LowLatency),
+            PresentMode::Fifo
+        );
+    }
+
+    #[test]
+    fn surface_format_falls_back_to_first_available() {
+        let available = [(Format::B8G8R8A8_UNORM, ColorSpace::SrgbNonLinear)];
+        assert_eq!(
+            Context::pick_surface_format(&available, &[]),
+            (Format::B8G8R8A8_UNORM, ColorSpace::SrgbNonLinear)
+        );
+    }
+
+    #[test]
+    fn feature_names_lists_enabled_features() {
+        let features = Features {
+            geometry_shader: true,
+            wide_lines: true,
+            ..Features::none()
+        };
+        assert_eq!(
+            Context::feature_names(&features),
+            ["geometry_shader", "wide_lines"]
+        );
+        assert!(Context::feature_names(&Features::none()).is_empty());
+    }
+
+    #[test]
+    fn optional_features_are_enabled_only_when_supported() {
+        let supported = Features {
+            geometry_shader: true,
+            wide_lines: true,
+            ..Features::none()
+        };
+        let required = Features {
+            geometry_shader: true,
+            ..Features::none()
+        };
+        let optional = Features {
+            wide_lines: true,
+            fill_mode_non_solid: true,
+            ..Features::none()
+        };
+        assert_eq!(
+            Context::enabled_features(&supported, &required, &optional),
+            supported
+        );
+        assert_eq!(
+            Context::enabled_features(&supported, &required, &Features::none()),
+            required
+        );
+    }
 }
@@ -0,0 +1,138 @@
+use ash::vk;
+use std::{fmt, ptr};
+use vulkano::{
+    device::{physical::PhysicalDevice, Device},
+    DeviceSize, Version, VulkanObject,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeapBudget {
+    pub usage: DeviceSize,
+    pub budget: DeviceSize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeapReport {
+    pub size: DeviceSize,
+    pub device_local: bool,
+    #[doc = "Only known when the device supports `VK_EXT_memory_budget`"]
+    pub budget: Option<HeapBudget>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemoryReport {
+    pub heaps: Vec<HeapReport>,
+}
+
+impl fmt::Display for MemoryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, heap) in self.heaps.iter().enumerate() {
+            let kind = if heap.device_local {
+                "device local"
+            } else {
+                "host"
+            };
+            match heap.budget {
+                Some(budget) => writeln!(
+                    f,
+                    "Heap {} ({}): {} MiB used of {} MiB budget, {} MiB total",
+                    index,
+                    kind,
+                    mebibytes(budget.usage),
+                    mebibytes(budget.budget),
+                    mebibytes(heap.size)
+                )?,
+                None => writeln!(
+                    f,
+                    "Heap {} ({}): {} MiB total",
+                    index,
+                    kind,
+                    mebibytes(heap.size)
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
+
+pub(super) fn report(device: &Device) -> MemoryReport {
+    let budgets = query_budgets(device);
+    MemoryReport {
+        heaps: device
+            .physical_device()
+            .memory_heaps()
+            .map(|heap| HeapReport {
+                size: heap.size(),
+                device_local: heap.is_device_local(),
+                budget: budgets.map(|budgets| HeapBudget {
+                    usage: budgets.heap_usage[heap.id() as usize],
+                    budget: budgets.heap_budget[heap.id() as usize],
+                }),
+            })
+            .collect(),
+    }
+}
+
+// Enabling the extension needs Vulkan 1.1 or an instance extension that isn't enabled, and the query below
+// uses the 1.1 function, so older devices fall back to heap sizes even if they advertise it
+pub(super) fn supports_budget(physical: PhysicalDevice) -> bool {
+    physical.supported_extensions().ext_memory_budget
+        && physical.instance().api_version() >= Version::V1_1
+        && physical.api_version() >= Version::V1_1
+}
+
+// vulkano doesn't wrap VK_EXT_memory_budget, so the query goes through the raw instance function
+fn query_budgets(device: &Device) -> Option<vk::PhysicalDeviceMemoryBudgetPropertiesEXT> {
+    let instance = device.instance();
+    let physical = device.physical_device();
+    if !(device.enabled_extensions().ext_memory_budget && supports_budget(physical)) {
+        return None;
+    }
+
+    let mut budgets = vk::PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+    let mut properties = vk::PhysicalDeviceMemoryProperties2::builder()
+        .push_next(&mut budgets)
+        .build();
+    unsafe {
+        (instance.fns().v1_1.get_physical_device_memory_properties2)(
+            physical.internal_object(),
+            ptr::addr_of_mut!(properties),
+        );
+    }
+    Some(budgets)
+}
+
+const fn mebibytes(bytes: DeviceSize) -> DeviceSize {
+    bytes / (1024 * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_falls_back_to_heap_size_without_budget() {
+        let report = MemoryReport {
+            heaps: vec![
+                HeapReport {
+                    size: 4096 * 1024 * 1024,
+                    device_local: true,
+                    budget: Some(HeapBudget {
+                        usage: 256 * 1024 * 1024,
+                        budget: 3072 * 1024 * 1024,
+                    }),
+                },
+                HeapReport {
+                    size: 512 * 1024 * 1024,
+                    device_local: false,
+                    budget: None,
+                },
+            ],
+        };
+        assert_eq!(
+            report.to_string(),
+            "Heap 0 (device local): 256 MiB used of 3072 MiB budget, 4096 MiB total\n\
+             Heap 1 (host): 512 MiB total\n"
+        );
+    }
+}
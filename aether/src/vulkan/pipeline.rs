@@ -0,0 +1,56 @@
+use eyre::{eyre, Result};
+use std::sync::Arc;
+use vulkano::{
+    pipeline::{
+        graphics::{
+            input_assembly::{InputAssemblyState, PrimitiveTopology},
+            rasterization::RasterizationState,
+            vertex_input::{BuffersDefinition, Vertex as VertexLayout},
+            viewport::{Viewport, ViewportState},
+        },
+        GraphicsPipeline, StateMode,
+    },
+    render_pass::{RenderPass, Subpass},
+    shader::EntryPoint,
+};
+
+use super::context::Context;
+
+#[derive(Clone, Debug)]
+pub struct PipelineCreateInfo<'a> {
+    #[doc = "Its inputs have to match the vertex type the pipeline is created for"]
+    pub vertex_shader: EntryPoint<'a>,
+    pub fragment_shader: EntryPoint<'a>,
+    pub viewport: Viewport,
+    pub render_pass: Arc<RenderPass>,
+    #[doc = "`PointList` needs the vertex shader to write `gl_PointSize`, otherwise the point size is undefined"]
+    pub topology: PrimitiveTopology,
+    pub line_width: f32,
+}
+
+#[doc = "Creates a pipeline drawing vertices of type `V`, which can be any `#[repr(C)]` type implementing vulkano's `Vertex`"]
+#[doc = "# Errors"]
+#[doc = "# Errors if `V` doesn't match the vertex shader's inputs, or if an internal vulkan error occurs"]
+pub fn create_pipeline<V: VertexLayout>(
+    ctx: &Context,
+    create_info: PipelineCreateInfo,
+) -> Result<Arc<GraphicsPipeline>> {
+    let subpass = Subpass::from(create_info.render_pass, 0)
+        .ok_or_else(|| eyre!("Failed to create pipeline because the render pass has no subpass"))?;
+
+    GraphicsPipeline::start()
+        .vertex_input_state(BuffersDefinition::new().vertex::<V>())
+        .vertex_shader(create_info.vertex_shader, ())
+        .input_assembly_state(InputAssemblyState::new().topology(create_info.topology))
+        .rasterization_state(RasterizationState {
+            line_width: StateMode::Fixed(ctx.line_width(create_info.line_width)),
+            ..RasterizationState::new()
+        })
+        .viewport_state(ViewportState::viewport_fixed_scissor_irrelevant([
+            create_info.viewport,
+        ]))
+        .fragment_shader(create_info.fragment_shader, ())
+        .render_pass(subpass)
+        .build(ctx.device.clone())
+        .map_err(|e| eyre!("Failed to create pipeline because {}", e))
+}
@@ -0,0 +1,289 @@
+use eyre::{eyre, Result};
+use std::{collections::VecDeque, sync::Arc};
+use vulkano::{
+    buffer::{BufferAccess, BufferUsage, CpuAccessibleBuffer},
+    command_buffer::{
+        AutoCommandBufferBuilder, CommandBufferExecFuture, CommandBufferUsage, CopyBufferInfo,
+        CopyBufferToImageInfo, PrimaryAutoCommandBuffer,
+    },
+    image::ImageAccess,
+    sync::{self, FenceSignalFuture, GpuFuture, NowFuture},
+    DeviceSize,
+};
+
+use super::context::Context;
+
+#[doc = "Keeps an upload's staging buffer alive until the GPU has finished copying from it"]
+#[doc = "# Blocking"]
+#[doc = "# Dropping a ticket blocks until the upload has finished"]
+#[must_use]
+pub struct UploadTicket {
+    future: FenceSignalFuture<CommandBufferExecFuture<NowFuture, PrimaryAutoCommandBuffer>>,
+    staging: Arc<CpuAccessibleBuffer<[u8]>>,
+}
+
+impl UploadTicket {
+    #[must_use]
+    pub fn staging_size(&self) -> DeviceSize {
+        self.staging.size()
+    }
+
+    #[doc = "# Errors"]
+    #[doc = "# Errors if the device runs out of memory while checking the fence"]
+    pub fn is_complete(&self) -> Result<bool> {
+        self.future
+            .is_signaled()
+            .map_err(|e| eyre!("Failed to check upload fence because {}", e))
+    }
+
+    #[doc = "# Errors"]
+    #[doc = "# Errors if the upload fails or the device is lost while waiting"]
+    pub fn wait(&self) -> Result<()> {
+        self.future
+            .wait(None)
+            .map_err(|e| eyre!("Failed waiting for upload to complete because {}", e))
+    }
+}
+
+#[doc = "Keeps the staging memory of outstanding uploads under a budget"]
+#[doc = "# Blocking"]
+#[doc = "# Blocks on the oldest uploads when a new one would exceed the budget, a single upload larger than the whole budget is still allowed once nothing else is in flight"]
+pub struct Uploader {
+    memory_budget: DeviceSize,
+    in_flight: VecDeque<UploadTicket>,
+}
+
+impl Uploader {
+    #[must_use]
+    pub const fn new(memory_budget: DeviceSize) -> Self {
+        Self {
+            memory_budget,
+            in_flight: VecDeque::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn in_flight_size(&self) -> DeviceSize {
+        self.in_flight.iter().map(UploadTicket::staging_size).sum()
+    }
+
+    #[doc = "# Errors"]
+    #[doc = "# Errors if waiting on an earlier upload fails, or if `upload_to_buffer` fails"]
+    pub fn upload_to_buffer(
+        &mut self,
+        ctx: &Context,
+        data: &[u8],
+        destination: Arc<dyn BufferAccess>,
+    ) -> Result<()> {
+        self.reserve(data.len() as DeviceSize)?;
+        self.in_flight
+            .push_back(upload_to_buffer(ctx, data, destination)?);
+        Ok(())
+    }
+
+    #[doc = "# Errors"]
+    #[doc = "# Errors if waiting on an earlier upload fails, or if `upload_to_image` fails"]
+    pub fn upload_to_image(
+        &mut self,
+        ctx: &Context,
+        data: &[u8],
+        destination: Arc<dyn ImageAccess>,
+    ) -> Result<()> {
+        self.reserve(data.len() as DeviceSize)?;
+        self.in_flight
+            .push_back(upload_to_image(ctx, data, destination)?);
+        Ok(())
+    }
+
+    #[doc = "Releases the staging memory of every upload that has finished, without blocking"]
+    #[doc = "# Errors"]
+    #[doc = "# Errors if an upload's fence can't be checked"]
+    pub fn poll(&mut self) -> Result<()> {
+        while let Some(ticket) = self.in_flight.front() {
+            if !ticket.is_complete()? {
+                break;
+            }
+            self.in_flight.pop_front();
+        }
+        Ok(())
+    }
+
+    #[doc = "# Errors"]
+    #[doc = "# Errors if any upload fails"]
+    pub fn wait_all(&mut self) -> Result<()> {
+        while let Some(ticket) = self.in_flight.pop_front() {
+            ticket.wait()?;
+        }
+        Ok(())
+    }
+
+    fn reserve(&mut self, size: DeviceSize) -> Result<()> {
+        self.poll()?;
+        let count = uploads_to_wait(
+            self.in_flight.iter().map(UploadTicket::staging_size),
+            size,
+            self.memory_budget,
+        );
+        // Popping one at a time keeps the tickets not yet waited on in flight if a wait fails, dropping them
+        // would wait again and panic on the same error
+        for _ in 0..count {
+            if let Some(ticket) = self.in_flight.pop_front() {
+                ticket.wait()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// How many of the oldest uploads have to finish before `incoming` bytes fit in the budget, waiting on all of them
+// admits an upload larger than the whole budget
+fn uploads_to_wait(
+    in_flight: impl Iterator<Item = DeviceSize>,
+    incoming: DeviceSize,
+    memory_budget: DeviceSize,
+) -> usize {
+    let sizes: Vec<DeviceSize> = in_flight.collect();
+    let mut total: DeviceSize = sizes.iter().sum();
+    let mut count = 0;
+    for size in sizes {
+        if total + incoming <= memory_budget {
+            break;
+        }
+        total -= size;
+        count += 1;
+    }
+    count
+}
+
+#[doc = "# Errors"]
+#[doc = "# Errors if `data` is empty, if `destination` is smaller than `data`, or if an internal vulkan error occurs"]
+pub fn upload_to_buffer(
+    ctx: &Context,
+    data: &[u8],
+    destination: Arc<dyn BufferAccess>,
+) -> Result<UploadTicket> {
+    check_not_empty(data)?;
+    if destination.size() < data.len() as DeviceSize {
+        return Err(eyre!(
+            "Upload of {} bytes does not fit in a {} byte buffer",
+            data.len(),
+            destination.size()
+        ));
+    }
+
+    let staging = create_staging_buffer(ctx, data)?;
+    let mut builder = create_builder(ctx)?;
+    builder
+        .copy_buffer(CopyBufferInfo::buffers(staging.clone(), destination))
+        .map_err(|e| eyre!("Failed to record buffer upload because {}", e))?;
+
+    submit(ctx, builder, staging)
+}
+
+#[doc = "# Errors"]
+#[doc = "# Errors if `data` is empty or does not match the image's extent and format, or if an internal vulkan error occurs"]
+pub fn upload_to_image(
+    ctx: &Context,
+    data: &[u8],
+    destination: Arc<dyn ImageAccess>,
+) -> Result<UploadTicket> {
+    check_not_empty(data)?;
+    let staging = create_staging_buffer(ctx, data)?;
+    let mut builder = create_builder(ctx)?;
+    builder
+        .copy_buffer_to_image(CopyBufferToImageInfo::buffer_image(
+            staging.clone(),
+            destination,
+        ))
+        .map_err(|e| eyre!("Failed to record image upload because {}", e))?;
+
+    submit(ctx, builder, staging)
+}
+
+// vulkano asserts that buffers aren't zero sized, so an empty upload would panic instead of returning an error
+fn check_not_empty(data: &[u8]) -> Result<()> {
+    if data.is_empty() {
+        Err(eyre!("Failed to upload because there is no data"))
+    } else {
+        Ok(())
+    }
+}
+
+fn create_staging_buffer(ctx: &Context, data: &[u8]) -> Result<Arc<CpuAccessibleBuffer<[u8]>>> {
+    CpuAccessibleBuffer::from_iter(
+        ctx.device.clone(),
+        BufferUsage::transfer_src(),
+        false,
+        data.iter().copied(),
+    )
+    .map_err(|e| eyre!("Failed to create staging buffer because {}", e))
+}
+
+fn create_builder(ctx: &Context) -> Result<AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>> {
+    AutoCommandBufferBuilder::primary(
+        ctx.device.clone(),
+        ctx.graphics.family(),
+        CommandBufferUsage::OneTimeSubmit,
+    )
+    .map_err(|e| {
+        eyre!(
+            "Failed to create upload command buffer builder because {}",
+            e
+        )
+    })
+}
+
+fn submit(
+    ctx: &Context,
+    builder: AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    staging: Arc<CpuAccessibleBuffer<[u8]>>,
+) -> Result<UploadTicket> {
+    let cmd = builder
+        .build()
+        .map_err(|e| eyre!("Failed to build upload command buffer because {}", e))?;
+
+    let future = sync::now(ctx.device.clone())
+        .then_execute(ctx.graphics.clone(), cmd)
+        .map_err(|e| eyre!("Failed to execute upload command buffer because {}", e))?
+        .then_signal_fence_and_flush()
+        .map_err(|e| eyre!("Failed to flush upload because {}", e))?;
+
+    Ok(UploadTicket { future, staging })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_upload_is_an_error() {
+        assert!(check_not_empty(&[]).is_err());
+        assert!(check_not_empty(&[0]).is_ok());
+    }
+
+    #[test]
+    fn tiny_budget_serializes_uploads() {
+        let mut in_flight = VecDeque::from([16]);
+        for _ in 0..4 {
+            let count = uploads_to_wait(in_flight.iter().copied(), 16, 0);
+            assert_eq!(count, 1);
+            in_flight.drain(..count);
+            in_flight.push_back(16);
+        }
+        assert_eq!(in_flight.len(), 1);
+    }
+
+    #[test]
+    fn waits_on_oldest_until_upload_fits() {
+        let in_flight = [32, 16, 16];
+        assert_eq!(uploads_to_wait(in_flight.iter().copied(), 16, 96), 0);
+        assert_eq!(uploads_to_wait(in_flight.iter().copied(), 48, 96), 1);
+        assert_eq!(uploads_to_wait(in_flight.iter().copied(), 72, 96), 2);
+    }
+
+    #[test]
+    fn oversized_upload_is_admitted_when_idle() {
+        assert_eq!(uploads_to_wait(std::iter::empty(), 1024, 16), 0);
+        assert_eq!(uploads_to_wait([8].into_iter(), 1024, 16), 1);
+    }
+}
@@ -2,6 +2,10 @@
 
 layout(location = 0) out vec4 f_color;
 
+layout(push_constant) uniform PushConstants {
+    vec4 tint;
+} push_constants;
+
 void main() {
-    f_color = vec4(1.0, 0.0, 0.0, 1.0);
+    f_color = vec4(1.0, 0.0, 0.0, 1.0) * push_constants.tint;
 }
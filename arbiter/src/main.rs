@@ -3,6 +3,7 @@
 #![warn(clippy::unwrap_used)]
 #![warn(clippy::expect_used)]
 
+use log::error;
 use std::sync::Arc;
 use vulkano::{
     buffer::{BufferUsage, CpuAccessibleBuffer},
@@ -11,23 +12,26 @@ use vulkano::{
     },
     image::view::ImageView,
     pipeline::{
-        graphics::{
-            input_assembly::InputAssemblyState,
-            vertex_input::BuffersDefinition,
-            viewport::{Viewport, ViewportState},
-        },
-        GraphicsPipeline,
+        graphics::{input_assembly::PrimitiveTopology, viewport::Viewport},
+        GraphicsPipeline, Pipeline,
     },
-    render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass},
-    swapchain::{AcquireError, SwapchainCreateInfo},
+    render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass},
+    swapchain::AcquireError,
     sync::{FlushError, GpuFuture},
 };
 use winit::{
-    event::{DeviceEvent, Event, WindowEvent},
+    event::{Event, VirtualKeyCode, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
 };
 
-use aether::vulkan::{context::Context, vertex::Vertex};
+use aether::{
+    input::Input,
+    vulkan::{
+        context::{Context, ContextCreateInfo},
+        pipeline::{create_pipeline, PipelineCreateInfo},
+        vertex::Vertex,
+    },
+};
 
 #[allow(clippy::needless_question_mark)]
 mod vs {
@@ -53,8 +57,12 @@ mod fs {
 
 layout(location = 0) out vec4 f_color;
 
+layout(push_constant) uniform PushConstants {
+    vec4 tint;
+} push_constants;
+
 void main() {
-    f_color = vec4(1.0, 0.0, 0.0, 1.0);
+    f_color = vec4(1.0, 0.0, 0.0, 1.0) * push_constants.tint;
 }"
     }
 }
@@ -62,9 +70,11 @@ void main() {
 // Temp code, allowing expect
 #[allow(clippy::expect_used)]
 fn get_pipeline(
-    vulkan_ctx: Context,
+    vulkan_ctx: &Context,
     viewport: Viewport,
     renderpass: Arc<RenderPass>,
+    topology: PrimitiveTopology,
+    line_width: f32,
 ) -> Arc<GraphicsPipeline> {
     let vs = match vs::load(vulkan_ctx.device.clone()) {
         Ok(shader) => shader,
@@ -75,25 +85,40 @@ fn get_pipeline(
         Err(e) => panic!("Failed to load fragment shader due to {}", e)
     };
 
-    match GraphicsPipeline::start()
-        .vertex_input_state(BuffersDefinition::new().vertex::<Vertex>())
-        .vertex_shader(vs.entry_point("main").expect("Failed to get entry point of vertex shader"), ())
-        .input_assembly_state(InputAssemblyState::new())
-        .viewport_state(ViewportState::viewport_fixed_scissor_irrelevant([viewport]))
-        .fragment_shader(fs.entry_point("main").expect("Failed to get entry point of fragment shader"), ())
-        .render_pass(Subpass::from(renderpass, 0).expect("Failed to create subpass info"))
-        .build(vulkan_ctx.device) {
-            Ok(pipeline) => pipeline,
-            Err(e) => panic!("Failed to create pipeline because {}", e)
-        }
+    match create_pipeline::<Vertex>(
+        vulkan_ctx,
+        PipelineCreateInfo {
+            vertex_shader: vs
+                .entry_point("main")
+                .expect("Failed to get entry point of vertex shader"),
+            fragment_shader: fs
+                .entry_point("main")
+                .expect("Failed to get entry point of fragment shader"),
+            viewport,
+            render_pass: renderpass,
+            topology,
+            line_width,
+        },
+    ) {
+        Ok(pipeline) => pipeline,
+        Err(e) => panic!("{}", e),
+    }
 }
 
 // Temporary code, allowing too many lines
 #[allow(clippy::too_many_lines)]
 fn main() {
+    env_logger::init();
+
     let event_loop = EventLoop::new();
 
-    let mut vulkan_ctx = match Context::new(&event_loop) {
+    let mut vulkan_ctx = match Context::new(
+        &event_loop,
+        &ContextCreateInfo {
+            title: Some("Arbiter".to_string()),
+            ..ContextCreateInfo::default()
+        },
+    ) {
         Ok(value) => value,
         Err(e) => panic!("Failed to create vulkan context because {}", e),
     };
@@ -136,16 +161,27 @@ fn main() {
 
     let mut viewport = Viewport {
         origin: [0.0, 0.0],
-        dimensions: vulkan_ctx.surface.window().inner_size().into(),
+        dimensions: vulkan_ctx.window().inner_size().into(),
         depth_range: 0.0..1.0,
     };
-    let mut pipeline = get_pipeline(vulkan_ctx.clone(), viewport.clone(), renderpass.clone());
+    let topology = PrimitiveTopology::TriangleList;
+    let line_width = 1.0;
+    let tint = [1.0, 1.0, 1.0, 1.0];
+    let mut pipeline = get_pipeline(
+        &vulkan_ctx,
+        viewport.clone(),
+        renderpass.clone(),
+        topology,
+        line_width,
+    );
 
+    let mut input = Input::new();
     let mut recreate_swapchain = false;
     let mut window_resized = false;
 
     event_loop.run(move |event, _, control_flow| {
         *control_flow = ControlFlow::Poll;
+        input.handle_event(&event);
 
         match event {
             Event::WindowEvent {
@@ -159,128 +195,131 @@ fn main() {
                 recreate_swapchain = true;
                 window_resized = true;
             }
-            Event::DeviceEvent {
-                event: DeviceEvent::Key(key),
-                ..
-            } => {
-                if key.state == winit::event::ElementState::Pressed
-                    && key.virtual_keycode == Some(winit::event::VirtualKeyCode::Escape)
-                {
+            Event::MainEventsCleared => {
+                if input.was_key_pressed(VirtualKeyCode::Escape) {
                     *control_flow = ControlFlow::Exit;
                 }
-            }
-            Event::MainEventsCleared => {
+                input.end_frame();
+
+                // A suboptimal image is still presented, the swapchain is only recreated here at the start of the
+                // next frame. An out of date swapchain can't be acquired from or presented to, so that frame is skipped
                 if recreate_swapchain {
                     recreate_swapchain = false;
-                    let dimensions = vulkan_ctx.surface.window().inner_size();
-                    let (new_swapchain, new_images) = match vulkan_ctx
-                        .swapchain
-                        .recreate(SwapchainCreateInfo {
-                            image_extent: dimensions.into(),
-                            ..vulkan_ctx.swapchain.create_info()
-                        }) {
-                            Ok(value) => value,
-                            Err(e) => panic!("Failed to recreate swapchain due to {}", e)
-                        };
-
-                    vulkan_ctx.swapchain = new_swapchain;
-                    vulkan_ctx.images = new_images;
+                    let new_extent = vulkan_ctx.window_size();
+                    if let Err(e) = vulkan_ctx.recreate_swapchain(new_extent) {
+                        panic!("Failed to recreate swapchain due to {}", e);
+                    }
 
                     if window_resized {
                         window_resized = false;
-                        viewport.dimensions = vulkan_ctx.surface.window().inner_size().into();
-                        pipeline =
-                            get_pipeline(vulkan_ctx.clone(), viewport.clone(), renderpass.clone());
-                    }
-
-                    let (image_idx, suboptimal, aquire_future) =
-                        match vulkano::swapchain::acquire_next_image(
-                            vulkan_ctx.swapchain.clone(),
-                            None,
-                        ) {
-                            Ok(r) => r,
-                            Err(AcquireError::OutOfDate) => {
-                                recreate_swapchain = true;
-                                return;
-                            }
-                            Err(e) => panic!("Failed to aquire swapchain image due to {}", e),
-                        };
-
-                    if suboptimal {
-                        recreate_swapchain = true;
+                        viewport.dimensions = vulkan_ctx.window().inner_size().into();
+                        pipeline = get_pipeline(
+                            &vulkan_ctx,
+                            viewport.clone(),
+                            renderpass.clone(),
+                            topology,
+                            line_width,
+                        );
                     }
+                }
 
-                    let mut builder = match AutoCommandBufferBuilder::primary(
-                        vulkan_ctx.device.clone(),
-                        vulkan_ctx.graphics.family(),
-                        CommandBufferUsage::MultipleSubmit,
+                let (image_idx, suboptimal, aquire_future) =
+                    match vulkano::swapchain::acquire_next_image(
+                        vulkan_ctx.swapchain.clone(),
+                        None,
                     ) {
-                        Ok(builder) => builder,
-                        Err(e) => panic!("Failed to create command buffer builder because {}", e)
+                        Ok(r) => r,
+                        Err(AcquireError::OutOfDate) => {
+                            recreate_swapchain = true;
+                            return;
+                        }
+                        Err(e) => panic!("Failed to aquire swapchain image due to {}", e),
                     };
 
-                    let view = match 
-                        ImageView::new_default(vulkan_ctx.images[image_idx].clone()) {
-                            Ok(view) => view,
-                            Err(e) => panic!("Failed to create swapchain image view because {}", e)
-                        };
-                    let framebuffer = match Framebuffer::new(
-                        renderpass.clone(),
-                        FramebufferCreateInfo {
-                            attachments: vec![view],
-                            ..Default::default()
-                        },
-                    ) {
-                        Ok(framebuffer) => framebuffer,
-                        Err(e) => panic!("Failed to create framebuffer due to {}", e)
-                    };
+                if suboptimal {
+                    recreate_swapchain = true;
+                }
 
-                    let pass_begin_info = RenderPassBeginInfo {
-                        clear_values: vec![Some([0.0, 0.0, 0.0, 1.0].into())],
-                        ..RenderPassBeginInfo::framebuffer(framebuffer)
-                    };
+                let mut builder = match AutoCommandBufferBuilder::primary(
+                    vulkan_ctx.device.clone(),
+                    vulkan_ctx.graphics.family(),
+                    CommandBufferUsage::MultipleSubmit,
+                ) {
+                    Ok(builder) => builder,
+                    Err(e) => panic!("Failed to create command buffer builder because {}", e)
+                };
 
-                    #[allow(clippy::expect_used)]
-                    builder
-                        .begin_render_pass(pass_begin_info, SubpassContents::Inline)
-                        .expect("Failed to begin render pass")
-                        .bind_pipeline_graphics(pipeline.clone())
-                        .bind_vertex_buffers(0, vertex_buffer.clone())
-                        .draw(3, 1, 0, 0)
-                        .expect("Failed to draw")
-                        .end_render_pass()
-                        .expect("Failed to end render pass");
-
-                    let cmd = match builder.build() {
-                        Ok(cmd) => cmd,
-                        Err(e) => panic!("Failed to build command buffer because {}", e)
+                let view = match 
+                    ImageView::new_default(vulkan_ctx.images[image_idx].clone()) {
+                        Ok(view) => view,
+                        Err(e) => panic!("Failed to create swapchain image view because {}", e)
                     };
+                let framebuffer = match Framebuffer::new(
+                    renderpass.clone(),
+                    FramebufferCreateInfo {
+                        attachments: vec![view],
+                        ..Default::default()
+                    },
+                ) {
+                    Ok(framebuffer) => framebuffer,
+                    Err(e) => panic!("Failed to create framebuffer due to {}", e)
+                };
 
-                    #[allow(clippy::expect_used)]
-                    let execution = vulkano::sync::now(vulkan_ctx.device.clone())
-                        .join(aquire_future)
-                        .then_execute(vulkan_ctx.graphics.clone(), cmd)
-                        .expect("Executing draw command buffer failed")
-                        .then_swapchain_present(
-                            vulkan_ctx.present.clone(),
-                            vulkan_ctx.swapchain.clone(),
-                            image_idx,
-                        )
-                        .then_signal_fence_and_flush();
-
-                    match execution {
-                        Ok(future) => {
-                            if let Err(e) = future.wait(None) {
-                                panic!("Error waiting for command buffer future because {}", e);
-                            }
-                        }
-                        Err(FlushError::OutOfDate) => {
-                            recreate_swapchain = true;
-                        }
-                        Err(e) => {
-                            println!("Failed to flush future: {:?}", e);
+                let pass_begin_info = RenderPassBeginInfo {
+                    clear_values: vec![Some([0.0, 0.0, 0.0, 1.0].into())],
+                    ..RenderPassBeginInfo::framebuffer(framebuffer)
+                };
+
+                #[allow(clippy::expect_used)]
+                builder
+                    .begin_render_pass(pass_begin_info, SubpassContents::Inline)
+                    .expect("Failed to begin render pass")
+                    .bind_pipeline_graphics(pipeline.clone())
+                    .push_constants(
+                        pipeline.layout().clone(),
+                        0,
+                        fs::ty::PushConstants { tint },
+                    )
+                    .bind_vertex_buffers(0, vertex_buffer.clone())
+                    .draw(3, 1, 0, 0)
+                    .expect("Failed to draw")
+                    .end_render_pass()
+                    .expect("Failed to end render pass");
+
+                let cmd = match builder.build() {
+                    Ok(cmd) => cmd,
+                    Err(e) => panic!("Failed to build command buffer because {}", e)
+                };
+
+                #[allow(clippy::expect_used)]
+                let execution = vulkano::sync::now(vulkan_ctx.device.clone())
+                    .join(aquire_future)
+                    .then_execute(vulkan_ctx.graphics.clone(), cmd)
+                    .expect("Executing draw command buffer failed")
+                    .then_swapchain_present(
+                        vulkan_ctx.present.clone(),
+                        vulkan_ctx.swapchain.clone(),
+                        image_idx,
+                    )
+                    .then_signal_fence_and_flush();
+
+                match execution {
+                    Ok(future) => {
+                        if let Err(e) = future.wait(None) {
+                            panic!("Error waiting for command buffer future because {}", e);
                         }
                     }
+                    Err(FlushError::OutOfDate) => {
+                        recreate_swapchain = true;
+                    }
+                    Err(e) => {
+                        error!("Failed to flush future: {:?}", e);
+                    }
+                }
+            }
+            Event::LoopDestroyed => {
+                if let Err(e) = vulkan_ctx.wait_idle() {
+                    panic!("Failed to wait for device on shutdown because {}", e);
                 }
             }
             _ => (),